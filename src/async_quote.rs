@@ -0,0 +1,72 @@
+// Copyright (C) 2023 Entropy Cryptography Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Async mirror of [`OpenQuote`]'s file I/O, gated behind the `async` feature.
+//!
+//! Reading `outblob` is what actually asks the TEE to produce the quote, which on TDX can block
+//! on a backend socket for a noticeable time. These methods do the same file operations as their
+//! sync counterparts but via `tokio::fs`, so an async caller offloads that wait to the blocking
+//! pool instead of stalling the executor thread.
+
+use crate::{trim_newline, OpenQuote, QuoteGenerationError};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+impl OpenQuote {
+    /// Async version of [`OpenQuote::write_input`]
+    pub async fn write_input_async(&mut self, input: [u8; 64]) -> Result<(), QuoteGenerationError> {
+        self.expected_generation = self.read_generation_async().await?;
+
+        let mut inblob_path = self.path.clone();
+        inblob_path.push("inblob");
+        let mut inblob_file = tokio::fs::File::create(inblob_path).await?;
+        inblob_file.write_all(&input).await?;
+        // tokio's File hands the write to the blocking pool and returns; wait for it to land
+        inblob_file.flush().await?;
+
+        self.expected_generation += 1;
+        Ok(())
+    }
+
+    /// Async version of [`OpenQuote::read_output`]
+    pub async fn read_output_async(&self) -> Result<Vec<u8>, QuoteGenerationError> {
+        let mut outblob_path = self.path.clone();
+        outblob_path.push("outblob");
+        let mut outblob_file = tokio::fs::File::open(outblob_path).await?;
+        let mut output = Vec::new();
+        outblob_file.read_to_end(&mut output).await?;
+
+        let actual = self.read_generation_async().await?;
+        if self.expected_generation != actual {
+            return Err(QuoteGenerationError::Generation(
+                self.expected_generation,
+                actual,
+            ));
+        }
+
+        if output.is_empty() {
+            return Err(QuoteGenerationError::EmptyQuote);
+        }
+        Ok(output)
+    }
+
+    /// Async version of [`OpenQuote::read_generation`]
+    pub async fn read_generation_async(&self) -> Result<u32, QuoteGenerationError> {
+        let mut generation_path = self.path.clone();
+        generation_path.push("generation");
+        let mut current_generation = tokio::fs::read_to_string(generation_path).await?;
+        trim_newline(&mut current_generation);
+        Ok(current_generation.parse()?)
+    }
+}
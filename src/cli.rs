@@ -0,0 +1,245 @@
+// Copyright (C) 2023 Entropy Cryptography Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A small self-contained getopt-style argument parser for the `configfs-tsm` binary.
+//!
+//! This is hand-rolled rather than pulled in from a crate like `clap` so that the binary keeps
+//! the library's zero-dependency promise.
+
+use std::fmt::{self, Display};
+
+/// Where the 64 bytes of quote input data should be read from
+pub(crate) enum InputSource {
+    /// Read the input from a file at the given path
+    File(String),
+    /// Read the input from stdin
+    Stdin,
+    /// The input, given directly as a hex string on the command line
+    Hex(String),
+}
+
+/// How the generated quote should be printed to stdout
+#[derive(Clone, Copy)]
+pub(crate) enum OutputFormat {
+    Raw,
+    Hex,
+    Base64,
+}
+
+impl OutputFormat {
+    fn parse(value: &str) -> Result<Self, CliError> {
+        match value {
+            "raw" => Ok(Self::Raw),
+            "hex" => Ok(Self::Hex),
+            "base64" => Ok(Self::Base64),
+            _ => Err(CliError::InvalidValue('o', value.to_string())),
+        }
+    }
+}
+
+/// Parsed command-line arguments
+pub(crate) struct Args {
+    /// Providers given with `-p`, passed to `create_quote_with_providers`. Empty means no
+    /// filtering, ie plain `create_quote`.
+    pub providers: Vec<String>,
+    /// Where to read the 64 byte input from, given with `-i` or `-x`
+    pub input: Option<InputSource>,
+    /// Quote directory name, given with `-n`
+    pub quote_name: Option<String>,
+    /// Output encoding, given with `-o`
+    pub output_format: OutputFormat,
+    /// Whether `-h`/`--help` was given
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            input: None,
+            quote_name: None,
+            output_format: OutputFormat::Raw,
+            help: false,
+        }
+    }
+}
+
+/// An error encountered while parsing command-line arguments
+#[derive(Debug)]
+pub(crate) enum CliError {
+    UnknownOption(String),
+    MissingValue(char),
+    InvalidValue(char, String),
+    UnexpectedArgument(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "Unknown option: {}", option),
+            CliError::MissingValue(flag) => write!(f, "Option -{} requires a value", flag),
+            CliError::InvalidValue(flag, value) => {
+                write!(f, "Invalid value for -{}: {}", flag, value)
+            }
+            CliError::UnexpectedArgument(argument) => {
+                write!(f, "Unexpected argument: {}", argument)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Parse `argv` (not including the program name) into [`Args`].
+///
+/// Supports clustered short flags (`-hp tdx_guest`), values joined to their flag (`-ptdx_guest`)
+/// or given as the next argument (`-p tdx_guest`), and `--` to terminate option parsing.
+pub(crate) fn parse(argv: &[String]) -> Result<Args, CliError> {
+    let mut args = Args::default();
+    let mut iter = argv.iter();
+
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            if let Some(leftover) = iter.next() {
+                return Err(CliError::UnexpectedArgument(leftover.clone()));
+            }
+            break;
+        }
+
+        if arg == "--help" {
+            args.help = true;
+            continue;
+        }
+
+        if let Some(rest) = arg.strip_prefix('-').filter(|rest| !rest.is_empty()) {
+            for (index, flag) in rest.char_indices() {
+                match flag {
+                    'h' => args.help = true,
+                    'p' | 'i' | 'x' | 'n' | 'o' => {
+                        let joined = &rest[index + flag.len_utf8()..];
+                        let value = if !joined.is_empty() {
+                            joined.to_string()
+                        } else {
+                            iter.next()
+                                .cloned()
+                                .ok_or(CliError::MissingValue(flag))?
+                        };
+                        apply_flag(&mut args, flag, value)?;
+                        break;
+                    }
+                    other => return Err(CliError::UnknownOption(format!("-{}", other))),
+                }
+            }
+            continue;
+        }
+
+        return Err(CliError::UnexpectedArgument(arg.clone()));
+    }
+
+    Ok(args)
+}
+
+/// Apply a single parsed flag and its value to `args`
+fn apply_flag(args: &mut Args, flag: char, value: String) -> Result<(), CliError> {
+    match flag {
+        'p' => args.providers.push(value),
+        'i' => {
+            args.input = Some(if value == "-" {
+                InputSource::Stdin
+            } else {
+                InputSource::File(value)
+            })
+        }
+        'x' => args.input = Some(InputSource::Hex(value)),
+        'n' => args.quote_name = Some(value),
+        'o' => args.output_format = OutputFormat::parse(&value)?,
+        _ => unreachable!("apply_flag called with a flag that was not dispatched to it"),
+    }
+    Ok(())
+}
+
+/// Usage text printed by `-h`/`--help`
+pub(crate) const USAGE: &str = "\
+configfs-tsm - generate a remote attestation quote via the Linux configfs-tsm interface
+
+USAGE:
+    configfs-tsm [OPTIONS]
+
+OPTIONS:
+    -p PROVIDER    Require the quote's provider to match PROVIDER (repeatable, eg -p tdx_guest)
+    -i FILE        Read the 64 byte input from FILE, or from stdin if FILE is '-'
+    -x HEX         Use HEX (a 128 character hex string) as the 64 byte input
+    -n NAME        Name of the quote directory (default: derived from the input)
+    -o FORMAT      Output encoding: raw, hex or base64 (default: raw)
+    -h, --help     Print this help text
+
+If neither -i nor -x is given, 64 null bytes are used as input.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &[&str]) -> Result<Args, CliError> {
+        parse(&args.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_separate_and_joined_values() {
+        let args = parse_str(&["-p", "tdx_guest", "-xdeadbeef", "-o", "hex"]).unwrap();
+        assert_eq!(args.providers, vec!["tdx_guest"]);
+        assert!(matches!(args.input, Some(InputSource::Hex(hex)) if hex == "deadbeef"));
+        assert!(matches!(args.output_format, OutputFormat::Hex));
+    }
+
+    #[test]
+    fn repeated_provider_flag_accumulates() {
+        let args = parse_str(&["-p", "tdx_guest", "-p", "sev_guest"]).unwrap();
+        assert_eq!(args.providers, vec!["tdx_guest", "sev_guest"]);
+    }
+
+    #[test]
+    fn clustered_help_flag() {
+        let args = parse_str(&["-hp", "tdx_guest"]).unwrap();
+        assert!(args.help);
+        assert_eq!(args.providers, vec!["tdx_guest"]);
+    }
+
+    #[test]
+    fn double_dash_ends_option_parsing() {
+        assert!(parse_str(&["--"]).is_ok());
+        assert!(matches!(
+            parse_str(&["--", "extra"]),
+            Err(CliError::UnexpectedArgument(arg)) if arg == "extra"
+        ));
+    }
+
+    #[test]
+    fn missing_value_is_an_error() {
+        assert!(matches!(parse_str(&["-n"]), Err(CliError::MissingValue('n'))));
+    }
+
+    #[test]
+    fn unknown_option_is_an_error() {
+        assert!(matches!(parse_str(&["-z"]), Err(CliError::UnknownOption(opt)) if opt == "-z"));
+    }
+
+    #[test]
+    fn invalid_output_format_is_an_error() {
+        assert!(matches!(
+            parse_str(&["-o", "yaml"]),
+            Err(CliError::InvalidValue('o', value)) if value == "yaml"
+        ));
+    }
+}
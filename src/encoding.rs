@@ -0,0 +1,124 @@
+// Copyright (C) 2023 Entropy Cryptography Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Hex and base64 helpers for the `configfs-tsm` binary, written by hand so the crate does not
+//! need to pull in an encoding dependency just for its CLI output.
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode `input` as a lowercase hex string
+pub(crate) fn hex_encode(input: &[u8]) -> String {
+    input.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string into bytes
+pub(crate) fn hex_decode(input: &str) -> Result<Vec<u8>, HexError> {
+    let bytes = input.trim().as_bytes();
+    if !bytes.len().is_multiple_of(2) {
+        return Err(HexError::OddLength);
+    }
+    bytes
+        .chunks(2)
+        .map(|pair| Ok(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+        .collect()
+}
+
+/// Parse a single ASCII hex digit into its 4 bit value
+fn hex_digit(byte: u8) -> Result<u8, HexError> {
+    match byte {
+        b'0'..=b'9' => Ok(byte - b'0'),
+        b'a'..=b'f' => Ok(byte - b'a' + 10),
+        b'A'..=b'F' => Ok(byte - b'A' + 10),
+        _ => Err(HexError::InvalidDigit),
+    }
+}
+
+/// Encode `input` as base64 (standard alphabet, with padding)
+pub(crate) fn base64_encode(input: &[u8]) -> String {
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = *chunk.get(1).unwrap_or(&0);
+        let b2 = *chunk.get(2).unwrap_or(&0);
+
+        output.push(BASE64_ALPHABET[(b0 >> 2) as usize] as char);
+        output.push(BASE64_ALPHABET[((b0 & 0x03) << 4 | b1 >> 4) as usize] as char);
+        output.push(if chunk.len() > 1 {
+            BASE64_ALPHABET[((b1 & 0x0f) << 2 | b2 >> 6) as usize] as char
+        } else {
+            '='
+        });
+        output.push(if chunk.len() > 2 {
+            BASE64_ALPHABET[(b2 & 0x3f) as usize] as char
+        } else {
+            '='
+        });
+    }
+    output
+}
+
+/// An error encountered while decoding a hex string
+#[derive(Debug)]
+pub(crate) enum HexError {
+    OddLength,
+    InvalidDigit,
+}
+
+impl std::fmt::Display for HexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HexError::OddLength => f.write_str("hex string must have an even number of digits"),
+            HexError::InvalidDigit => f.write_str("hex string contains a non-hex digit"),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_roundtrip() {
+        let bytes = [0u8, 1, 15, 16, 255];
+        assert_eq!(hex_decode(&hex_encode(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn hex_decode_rejects_odd_length() {
+        assert!(matches!(hex_decode("abc"), Err(HexError::OddLength)));
+    }
+
+    #[test]
+    fn hex_decode_rejects_non_ascii_without_panicking() {
+        // A multi-byte UTF-8 character must not be sliced on a non-char-boundary byte offset.
+        assert!(matches!(hex_decode("€0"), Err(HexError::InvalidDigit)));
+    }
+
+    #[test]
+    fn hex_decode_rejects_non_hex_digit() {
+        assert!(matches!(hex_decode("zz"), Err(HexError::InvalidDigit)));
+    }
+
+    #[test]
+    fn base64_matches_known_vectors() {
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+}
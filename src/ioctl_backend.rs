@@ -0,0 +1,121 @@
+// Copyright (C) 2023 Entropy Cryptography Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A [`QuoteBackend`] that talks directly to the TDX guest device (`/dev/tdx_guest`) via its
+//! report ioctl, for use on guests where `configfs-tsm` is not mounted.
+//!
+//! The ioctl number and request layout below come from the kernel's `tdx-guest.h` uapi header
+//! (`TDX_CMD_GET_REPORT0`). We declare our own `extern "C"` binding to `ioctl` rather than
+//! depending on the `libc` crate, so this still costs no extra dependency in the default build.
+
+use crate::{QuoteBackend, QuoteGenerationError};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+
+/// Path of the TDX guest device
+const TDX_GUEST_DEVICE_PATH: &str = "/dev/tdx_guest";
+
+/// `TDX_CMD_GET_REPORT0`, ie `_IOWR('T', 1, struct tdx_report_req)`
+const TDX_CMD_GET_REPORT0: std::ffi::c_ulong = 0xc440_5401;
+
+/// Size of the `tdreport` field of `struct tdx_report_req`
+const TDX_REPORT_LEN: usize = 1024;
+
+#[repr(C)]
+struct TdxReportReq {
+    /// The 64 byte input / report data, supplied by the caller
+    reportdata: [u8; 64],
+    /// The TDREPORT produced by the TDX module
+    tdreport: [u8; TDX_REPORT_LEN],
+}
+
+extern "C" {
+    fn ioctl(fd: std::ffi::c_int, request: std::ffi::c_ulong, ...) -> std::ffi::c_int;
+}
+
+/// A quote backend that reads the report directly from the TDX guest device, without going
+/// through `configfs-tsm`
+pub struct TdxGuestIoctlQuote {
+    device: File,
+    report: Option<[u8; TDX_REPORT_LEN]>,
+}
+
+impl TdxGuestIoctlQuote {
+    /// Open the TDX guest device
+    pub fn new() -> Result<Self, QuoteGenerationError> {
+        let device = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(TDX_GUEST_DEVICE_PATH)
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => QuoteGenerationError::CannotFindTdxGuestDevice,
+                _ => QuoteGenerationError::IO(error),
+            })?;
+        Ok(Self {
+            device,
+            report: None,
+        })
+    }
+}
+
+impl QuoteBackend for TdxGuestIoctlQuote {
+    /// Issue the `TDX_CMD_GET_REPORT0` ioctl with `input` as the report data
+    fn write_input(&mut self, input: [u8; 64]) -> Result<(), QuoteGenerationError> {
+        let mut request = TdxReportReq {
+            reportdata: input,
+            tdreport: [0u8; TDX_REPORT_LEN],
+        };
+
+        // SAFETY: `device` is a valid, open file descriptor for /dev/tdx_guest, and `request` is
+        // a valid pointer to a correctly sized buffer for the duration of this call.
+        let result = unsafe {
+            ioctl(
+                self.device.as_raw_fd(),
+                TDX_CMD_GET_REPORT0,
+                &mut request as *mut TdxReportReq,
+            )
+        };
+        if result < 0 {
+            return Err(QuoteGenerationError::IO(io::Error::last_os_error()));
+        }
+
+        self.report = Some(request.tdreport);
+        Ok(())
+    }
+
+    /// Return the TDREPORT produced by the most recent `write_input` call
+    fn read_output(&self) -> Result<Vec<u8>, QuoteGenerationError> {
+        match &self.report {
+            Some(report) => Ok(report.to_vec()),
+            None => Err(QuoteGenerationError::EmptyQuote),
+        }
+    }
+
+    /// The ioctl interface has no generation/conflict concept: every call returns a report for
+    /// the input given in that same call, with nothing else to race against
+    fn read_generation(&self) -> Result<u32, QuoteGenerationError> {
+        Ok(0)
+    }
+
+    /// The TDX guest device can only ever produce a TDX quote, so this only accepts `tdx_guest`
+    fn check_provider(&self, accepted_values: Vec<&str>) -> Result<(), QuoteGenerationError> {
+        if accepted_values.contains(&"tdx_guest") {
+            Ok(())
+        } else {
+            Err(QuoteGenerationError::BadProvider("tdx_guest".to_string()))
+        }
+    }
+}
@@ -20,13 +20,25 @@
 //! This is designed for and tested with Intel TDX, but since the `configfs-tsm` is a platform-agnostic
 //! interface, this could potentially work with other platforms such as Intel SGX, or AMD SEV.
 //!
-//! This crate has no dependencies and generates quotes only by reading and writing local files.
+//! By default this crate has no dependencies and generates quotes by reading and writing local
+//! files, or through an ioctl on the TDX guest device (see below). Enabling the `async` feature additionally pulls in `tokio` and exposes async
+//! equivalents of [`OpenQuote`]'s file I/O, for callers who don't want to block an executor
+//! thread while the TEE produces a quote.
+//!
+//! Quote generation goes through a [`QuoteBackend`]: [`OpenQuote`] (the `configfs-tsm` interface)
+//! by default, with [`ioctl_backend::TdxGuestIoctlQuote`] (the TDX guest device ioctl) available
+//! as a fallback on guests where `configfs-tsm` is not mounted. See [`Backend`].
 //!
 //! Warning: This crate is in early stages of development and has not been audited
+#[cfg(feature = "async")]
+mod async_quote;
+pub mod ioctl_backend;
+pub mod net;
+
 use std::{
     error::Error,
     fmt::{self, Display},
-    fs::{create_dir, read_to_string, File},
+    fs::{create_dir, read_to_string, remove_dir, File},
     hash::{DefaultHasher, Hash, Hasher},
     io::{ErrorKind, Read, Write},
     num::ParseIntError,
@@ -38,10 +50,7 @@ const CONFIGFS_TSM_PATH: &str = "/sys/kernel/config/tsm/report";
 
 /// Create a quote with given input, using the input data as quote directory name
 pub fn create_quote(input: [u8; 64]) -> Result<Vec<u8>, QuoteGenerationError> {
-    let quote_name = create_quote_name(&input);
-    let mut quote = OpenQuote::new(&quote_name)?;
-    quote.write_input(input)?;
-    quote.read_output()
+    create_quote_inner(input, None, Backend::Configfs)
 }
 
 /// Same as create_quote, but check that the provider (the TEE platform) matches one of a given set
@@ -50,11 +59,26 @@ pub fn create_quote_with_providers(
     input: [u8; 64],
     accepted_providers: Vec<&str>,
 ) -> Result<Vec<u8>, QuoteGenerationError> {
-    let quote_name = create_quote_name(&input);
-    let mut quote = OpenQuote::new(&quote_name)?;
-    quote.check_provider(accepted_providers)?;
-    quote.write_input(input)?;
-    quote.read_output()
+    create_quote_inner(input, Some(accepted_providers), Backend::Configfs)
+}
+
+/// Same as create_quote, but using an explicitly chosen [`Backend`] instead of always going
+/// through `configfs-tsm`
+pub fn create_quote_with_backend(
+    input: [u8; 64],
+    backend: Backend,
+) -> Result<Vec<u8>, QuoteGenerationError> {
+    create_quote_inner(input, None, backend)
+}
+
+/// Same as create_quote_with_providers, but using an explicitly chosen [`Backend`] instead of
+/// always going through `configfs-tsm`
+pub fn create_quote_with_providers_and_backend(
+    input: [u8; 64],
+    accepted_providers: Vec<&str>,
+    backend: Backend,
+) -> Result<Vec<u8>, QuoteGenerationError> {
+    create_quote_inner(input, Some(accepted_providers), backend)
 }
 
 /// Convenience function for creating a quote and checking the provider is tdx_guest
@@ -62,6 +86,80 @@ pub fn create_tdx_quote(input: [u8; 64]) -> Result<Vec<u8>, QuoteGenerationError
     create_quote_with_providers(input, vec!["tdx_guest"])
 }
 
+fn create_quote_inner(
+    input: [u8; 64],
+    accepted_providers: Option<Vec<&str>>,
+    backend: Backend,
+) -> Result<Vec<u8>, QuoteGenerationError> {
+    let quote_name = create_quote_name(&input);
+    let mut quote = open_backend(&quote_name, backend)?;
+    if let Some(accepted_providers) = accepted_providers {
+        quote.check_provider(accepted_providers)?;
+    }
+    quote.write_input(input)?;
+    quote.read_output()
+}
+
+/// Which mechanism to use to produce a quote
+pub enum Backend {
+    /// Use the `configfs-tsm` filesystem interface ([`OpenQuote`])
+    Configfs,
+    /// Use the TDX guest device's report ioctl directly ([`ioctl_backend::TdxGuestIoctlQuote`]).
+    /// The output is then the 1024 byte TDREPORT returned by that ioctl.
+    TdxGuestIoctl,
+    /// Use `configfs-tsm` if it is mounted, falling back to the TDX guest device ioctl otherwise
+    Auto,
+}
+
+/// Open a quote under the chosen backend
+fn open_backend(
+    quote_name: &str,
+    backend: Backend,
+) -> Result<Box<dyn QuoteBackend>, QuoteGenerationError> {
+    match backend {
+        Backend::Configfs => Ok(Box::new(OpenQuote::new(quote_name)?)),
+        Backend::TdxGuestIoctl => Ok(Box::new(ioctl_backend::TdxGuestIoctlQuote::new()?)),
+        Backend::Auto => match OpenQuote::new(quote_name) {
+            Ok(quote) => Ok(Box::new(quote)),
+            Err(QuoteGenerationError::CannotFindTsmDir) => {
+                Ok(Box::new(ioctl_backend::TdxGuestIoctlQuote::new()?))
+            }
+            Err(error) => Err(error),
+        },
+    }
+}
+
+/// A mechanism capable of producing a quote. [`OpenQuote`] (the `configfs-tsm` interface) is the
+/// default implementation; see [`Backend`] for the others.
+pub trait QuoteBackend {
+    /// Write the 64 byte input/nonce used to generate the quote
+    fn write_input(&mut self, input: [u8; 64]) -> Result<(), QuoteGenerationError>;
+    /// Generate the quote
+    fn read_output(&self) -> Result<Vec<u8>, QuoteGenerationError>;
+    /// Read the current generation number, used to detect conflicts
+    fn read_generation(&self) -> Result<u32, QuoteGenerationError>;
+    /// Check that the provider matches one of the accepted values
+    fn check_provider(&self, accepted_values: Vec<&str>) -> Result<(), QuoteGenerationError>;
+}
+
+impl QuoteBackend for OpenQuote {
+    fn write_input(&mut self, input: [u8; 64]) -> Result<(), QuoteGenerationError> {
+        OpenQuote::write_input(self, input)
+    }
+
+    fn read_output(&self) -> Result<Vec<u8>, QuoteGenerationError> {
+        OpenQuote::read_output(self)
+    }
+
+    fn read_generation(&self) -> Result<u32, QuoteGenerationError> {
+        OpenQuote::read_generation(self)
+    }
+
+    fn check_provider(&self, accepted_values: Vec<&str>) -> Result<(), QuoteGenerationError> {
+        OpenQuote::check_provider(self, accepted_values)
+    }
+}
+
 /// Represents a pending quote
 pub struct OpenQuote {
     /// The path of the quote files
@@ -69,6 +167,8 @@ pub struct OpenQuote {
     /// What generation number we expect the quote to have when reading.
     /// This is used to detect conflicts when another process modifies the quote.
     expected_generation: u32,
+    /// If true, the quote directory is not removed when this is dropped
+    keep: bool,
 }
 
 impl OpenQuote {
@@ -90,9 +190,19 @@ impl OpenQuote {
         Ok(Self {
             path: quote_path,
             expected_generation: 0,
+            keep: false,
         })
     }
 
+    /// Mark this quote to be kept on drop, instead of having its configfs directory removed.
+    ///
+    /// Use this when the quote directory is intentionally being named (see [`OpenQuote::new`])
+    /// so that it can be re-used across processes.
+    pub fn persist(mut self) -> Self {
+        self.keep = true;
+        self
+    }
+
     /// Write input data to quote
     pub fn write_input(&mut self, input: [u8; 64]) -> Result<(), QuoteGenerationError> {
         self.update_generation()?;
@@ -155,6 +265,25 @@ impl OpenQuote {
     }
 }
 
+impl Drop for OpenQuote {
+    /// Remove the quote's configfs directory, unless it was marked with [`OpenQuote::persist`].
+    ///
+    /// configfs-tsm only frees a report entry when its directory is removed, so without this a
+    /// long-running process which creates many distinct quotes would leak one directory per
+    /// quote for as long as the mount is alive.
+    fn drop(&mut self) {
+        if self.keep {
+            return;
+        }
+        // Best-effort: a destructor cannot propagate errors, and a directory that is already
+        // gone is not a problem worth reporting.
+        let _ = remove_dir(&self.path).or_else(|error| match error.kind() {
+            ErrorKind::NotFound => Ok(()),
+            _ => Err(error),
+        });
+    }
+}
+
 /// Derive a name for the quote directory from the input data by hashing and encoding as hex
 fn create_quote_name(input: &[u8]) -> String {
     let mut s = DefaultHasher::new();
@@ -189,6 +318,7 @@ pub enum QuoteGenerationError {
     ParseInt,
     BadProvider(String),
     CannotFindTsmDir,
+    CannotFindTdxGuestDevice,
     EmptyQuote,
 }
 
@@ -210,6 +340,9 @@ impl Display for QuoteGenerationError {
             QuoteGenerationError::CannotFindTsmDir => f.write_str(
                 "Cannot find configfs-tsm directory - maybe your hardware does not support it",
             ),
+            QuoteGenerationError::CannotFindTdxGuestDevice => f.write_str(
+                "Cannot find /dev/tdx_guest - maybe your hardware does not support it",
+            ),
             QuoteGenerationError::EmptyQuote => f.write_str("Empty quote. This could be an authorization issue with the quote generation socket."),
         }
     }
@@ -13,17 +13,118 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use configfs_tsm::OpenQuote;
+mod cli;
+mod encoding;
 
-fn main() {
-    // If an argument is given it is used as the quote name
-    let quote_name = std::env::args().nth(1).unwrap_or("test-quote".to_string());
-    let mut quote = OpenQuote::new(&quote_name).unwrap();
+use cli::{Args, InputSource, OutputFormat};
+use configfs_tsm::{create_quote, create_quote_with_providers, OpenQuote};
+use std::io::Read;
+use std::process::ExitCode;
 
-    // Give 64 null bytes as input data
-    quote.write_input([0; 64]).unwrap();
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let args = match cli::parse(&argv) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            eprintln!("{}", cli::USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let output = quote.read_output().unwrap();
-    println!("Quote: {:?}", output);
-    println!("Generation: {}", quote.read_generation().unwrap());
+    if args.help {
+        println!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    run(args)
+}
+
+fn run(args: Args) -> ExitCode {
+    let input = match read_input(args.input) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let quote_result = match args.quote_name {
+        // A custom quote directory name was given: drive OpenQuote by hand, since
+        // create_quote/create_quote_with_providers always derive the name from the input.
+        Some(quote_name) => create_named_quote(&quote_name, input, &args.providers),
+        None if args.providers.is_empty() => create_quote(input),
+        None => {
+            let accepted_providers = args.providers.iter().map(String::as_str).collect();
+            create_quote_with_providers(input, accepted_providers)
+        }
+    };
+
+    let quote = match quote_result {
+        Ok(quote) => quote,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    print_quote(&quote, args.output_format);
+    ExitCode::SUCCESS
+}
+
+/// Create a quote under an explicitly named directory, optionally checking the provider
+fn create_named_quote(
+    quote_name: &str,
+    input: [u8; 64],
+    accepted_providers: &[String],
+) -> Result<Vec<u8>, configfs_tsm::QuoteGenerationError> {
+    // A caller who names the quote directory explicitly (`-n`) is asking to be able to re-use it
+    // across processes, so keep it around instead of having Drop remove it on exit.
+    let mut quote = OpenQuote::new(quote_name)?.persist();
+    if !accepted_providers.is_empty() {
+        let accepted_providers = accepted_providers.iter().map(String::as_str).collect();
+        quote.check_provider(accepted_providers)?;
+    }
+    quote.write_input(input)?;
+    quote.read_output()
+}
+
+/// Read the 64 byte quote input from the source given on the command line, defaulting to 64
+/// null bytes if neither `-i` nor `-x` was given
+fn read_input(source: Option<InputSource>) -> Result<[u8; 64], String> {
+    let bytes = match source {
+        None => vec![0u8; 64],
+        Some(InputSource::Hex(hex)) => {
+            encoding::hex_decode(&hex).map_err(|error| format!("invalid -x value: {}", error))?
+        }
+        Some(InputSource::Stdin) => {
+            let mut buffer = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut buffer)
+                .map_err(|error| format!("could not read stdin: {}", error))?;
+            buffer
+        }
+        Some(InputSource::File(path)) => {
+            std::fs::read(&path).map_err(|error| format!("could not read {}: {}", path, error))?
+        }
+    };
+
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        format!(
+            "quote input must be exactly 64 bytes, got {}",
+            bytes.len()
+        )
+    })
+}
+
+/// Print `quote` to stdout encoded per `format`
+fn print_quote(quote: &[u8], format: OutputFormat) {
+    match format {
+        OutputFormat::Raw => {
+            use std::io::Write;
+            std::io::stdout().write_all(quote).ok();
+        }
+        OutputFormat::Hex => println!("{}", encoding::hex_encode(quote)),
+        OutputFormat::Base64 => println!("{}", encoding::base64_encode(quote)),
+    }
 }
@@ -0,0 +1,342 @@
+// Copyright (C) 2023 Entropy Cryptography Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A tiny client/server subsystem for generating quotes over a socket, so a relying party (or a
+//! process without filesystem access to configfs) can ask for an attestation quote without
+//! talking to `/sys/kernel/config/tsm/report` directly.
+//!
+//! The wire protocol is deliberately minimal and length-prefixed:
+//!
+//! ```text
+//! request:  [ 64 bytes input ][ 1 byte provider count ][ provider ]*
+//!           provider = [ 1 byte len ][ len bytes utf8 ]
+//! response: [ 1 byte status ][ body ]
+//!           status == 0 (Ok):    body = [ 4 bytes LE length ][ length bytes quote ]
+//!           status != 0 (Error): body = [ 2 bytes LE length ][ length bytes utf8 message ]
+//! ```
+//!
+//! Both a Unix domain socket and TCP are supported, via the same generic read/write framing
+//! over anything that implements [`Read`] and [`Write`].
+
+use crate::{create_quote, create_quote_with_providers, QuoteGenerationError};
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+/// Handle a single request on an already-connected stream: read the input and accepted
+/// providers, generate the quote, and write back the response.
+fn handle_connection<S: Read + Write>(stream: &mut S) -> io::Result<()> {
+    let (input, accepted_providers) = read_request(stream)?;
+    let accepted_providers: Vec<&str> = accepted_providers.iter().map(String::as_str).collect();
+
+    let result = if accepted_providers.is_empty() {
+        create_quote(input)
+    } else {
+        create_quote_with_providers(input, accepted_providers)
+    };
+
+    write_response(stream, &result)
+}
+
+/// Listen on a Unix domain socket and serve quote requests, one at a time, forever
+pub fn serve_unix(socket_path: &Path) -> io::Result<()> {
+    let listener = UnixListener::bind(socket_path)?;
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let _ = handle_connection(&mut stream);
+    }
+    Ok(())
+}
+
+/// Listen on a TCP address and serve quote requests, one at a time, forever
+pub fn serve_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let _ = handle_connection(&mut stream);
+    }
+    Ok(())
+}
+
+/// Connect to a quote server over a Unix domain socket and request a quote for `input`
+pub fn request_quote_over_unix(
+    socket_path: &Path,
+    input: [u8; 64],
+    accepted_providers: &[&str],
+) -> Result<Vec<u8>, QuoteServiceError> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    write_request(&mut stream, &input, accepted_providers)?;
+    read_response(&mut stream)
+}
+
+/// Connect to a quote server over TCP and request a quote for `input`
+pub fn request_quote_over_tcp<A: ToSocketAddrs>(
+    addr: A,
+    input: [u8; 64],
+    accepted_providers: &[&str],
+) -> Result<Vec<u8>, QuoteServiceError> {
+    let mut stream = TcpStream::connect(addr)?;
+    write_request(&mut stream, &input, accepted_providers)?;
+    read_response(&mut stream)
+}
+
+/// Write a request: the 64 byte input followed by the accepted provider list
+fn write_request<W: Write>(writer: &mut W, input: &[u8; 64], accepted_providers: &[&str]) -> io::Result<()> {
+    writer.write_all(input)?;
+    writer.write_all(&[accepted_providers.len() as u8])?;
+    for provider in accepted_providers {
+        writer.write_all(&[provider.len() as u8])?;
+        writer.write_all(provider.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Read a request as written by [`write_request`]
+fn read_request<R: Read>(reader: &mut R) -> io::Result<([u8; 64], Vec<String>)> {
+    let mut input = [0u8; 64];
+    reader.read_exact(&mut input)?;
+
+    let mut provider_count = [0u8; 1];
+    reader.read_exact(&mut provider_count)?;
+
+    let mut accepted_providers = Vec::with_capacity(provider_count[0] as usize);
+    for _ in 0..provider_count[0] {
+        let mut len = [0u8; 1];
+        reader.read_exact(&mut len)?;
+        let mut provider = vec![0u8; len[0] as usize];
+        reader.read_exact(&mut provider)?;
+        accepted_providers.push(String::from_utf8_lossy(&provider).into_owned());
+    }
+
+    Ok((input, accepted_providers))
+}
+
+/// Write a response: either the quote bytes, or an error status and message
+fn write_response<W: Write>(
+    writer: &mut W,
+    result: &Result<Vec<u8>, QuoteGenerationError>,
+) -> io::Result<()> {
+    match result {
+        Ok(quote) => {
+            writer.write_all(&[0u8])?;
+            writer.write_all(&(quote.len() as u32).to_le_bytes())?;
+            writer.write_all(quote)?;
+        }
+        Err(error) => {
+            let message = error.to_string();
+            writer.write_all(&[error_status(error)])?;
+            writer.write_all(&(message.len() as u16).to_le_bytes())?;
+            writer.write_all(message.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// The largest response body this client will allocate for. Real quotes are at most a few KB, so
+/// this is generous headroom while still bounding what an untrusted or buggy server can make the
+/// client allocate.
+const MAX_RESPONSE_LEN: u32 = 64 * 1024;
+
+/// Read a response as written by [`write_response`]
+fn read_response<R: Read>(reader: &mut R) -> Result<Vec<u8>, QuoteServiceError> {
+    let mut status = [0u8; 1];
+    reader.read_exact(&mut status)?;
+
+    if status[0] == 0 {
+        let mut len_bytes = [0u8; 4];
+        reader.read_exact(&mut len_bytes)?;
+        let len = u32::from_le_bytes(len_bytes);
+        if len > MAX_RESPONSE_LEN {
+            return Err(QuoteServiceError::ResponseTooLarge(len));
+        }
+        let mut quote = vec![0u8; len as usize];
+        reader.read_exact(&mut quote)?;
+        Ok(quote)
+    } else {
+        let mut len_bytes = [0u8; 2];
+        reader.read_exact(&mut len_bytes)?;
+        let len = u16::from_le_bytes(len_bytes) as u32;
+        if len > MAX_RESPONSE_LEN {
+            return Err(QuoteServiceError::ResponseTooLarge(len));
+        }
+        let mut message = vec![0u8; len as usize];
+        reader.read_exact(&mut message)?;
+        Err(QuoteServiceError::Remote(
+            RemoteErrorKind::from_status(status[0]),
+            String::from_utf8_lossy(&message).into_owned(),
+        ))
+    }
+}
+
+/// Map a [`QuoteGenerationError`] to a non-zero status byte sent over the wire. Kept in sync
+/// with [`RemoteErrorKind::from_status`], its inverse.
+fn error_status(error: &QuoteGenerationError) -> u8 {
+    match error {
+        QuoteGenerationError::Generation(_, _) => 1,
+        QuoteGenerationError::IO(_) => 2,
+        QuoteGenerationError::ParseInt => 3,
+        QuoteGenerationError::BadProvider(_) => 4,
+        QuoteGenerationError::CannotFindTsmDir => 5,
+        QuoteGenerationError::CannotFindTdxGuestDevice => 6,
+        QuoteGenerationError::EmptyQuote => 7,
+    }
+}
+
+/// The kind of [`QuoteGenerationError`] the server hit, decoded from the wire status byte, so a
+/// relying party can distinguish failure kinds without string-matching the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteErrorKind {
+    Generation,
+    IO,
+    ParseInt,
+    BadProvider,
+    CannotFindTsmDir,
+    CannotFindTdxGuestDevice,
+    EmptyQuote,
+    /// A status byte this client doesn't recognise, eg from a newer server
+    Unknown(u8),
+}
+
+impl RemoteErrorKind {
+    /// Decode a status byte as written by [`error_status`], its inverse
+    fn from_status(status: u8) -> Self {
+        match status {
+            1 => Self::Generation,
+            2 => Self::IO,
+            3 => Self::ParseInt,
+            4 => Self::BadProvider,
+            5 => Self::CannotFindTsmDir,
+            6 => Self::CannotFindTdxGuestDevice,
+            7 => Self::EmptyQuote,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// An error from the client side of the quote-over-socket protocol
+#[derive(Debug)]
+pub enum QuoteServiceError {
+    /// A transport-level I/O error talking to the server
+    IO(io::Error),
+    /// The server ran quote generation and returned an error: its kind, decoded from the wire
+    /// status byte, and its display message
+    Remote(RemoteErrorKind, String),
+    /// The server's response claimed a quote or message longer than [`MAX_RESPONSE_LEN`], which
+    /// is refused before it is read rather than trusted for an allocation
+    ResponseTooLarge(u32),
+}
+
+impl Display for QuoteServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteServiceError::IO(error) => f.write_str(&error.to_string()),
+            QuoteServiceError::Remote(kind, message) => {
+                write!(f, "quote server returned {:?}: {}", kind, message)
+            }
+            QuoteServiceError::ResponseTooLarge(len) => write!(
+                f,
+                "quote server's response claimed a length of {} bytes, which exceeds the maximum of {} bytes",
+                len, MAX_RESPONSE_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuoteServiceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IO(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for QuoteServiceError {
+    fn from(error: io::Error) -> QuoteServiceError {
+        QuoteServiceError::IO(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn request_roundtrips_with_providers() {
+        let input = [7u8; 64];
+        let mut buffer = Vec::new();
+        write_request(&mut buffer, &input, &["tdx_guest", "sev_guest"]).unwrap();
+
+        let (decoded_input, decoded_providers) = read_request(&mut Cursor::new(buffer)).unwrap();
+        assert_eq!(decoded_input, input);
+        assert_eq!(decoded_providers, vec!["tdx_guest", "sev_guest"]);
+    }
+
+    #[test]
+    fn request_roundtrips_with_no_providers() {
+        let input = [0u8; 64];
+        let mut buffer = Vec::new();
+        write_request(&mut buffer, &input, &[]).unwrap();
+
+        let (decoded_input, decoded_providers) = read_request(&mut Cursor::new(buffer)).unwrap();
+        assert_eq!(decoded_input, input);
+        assert!(decoded_providers.is_empty());
+    }
+
+    #[test]
+    fn response_roundtrips_ok_quote() {
+        let quote = vec![1, 2, 3, 4, 5];
+        let mut buffer = Vec::new();
+        write_response(&mut buffer, &Ok(quote.clone())).unwrap();
+
+        assert_eq!(read_response(&mut Cursor::new(buffer)).unwrap(), quote);
+    }
+
+    #[test]
+    fn response_roundtrips_error_with_kind() {
+        let mut buffer = Vec::new();
+        write_response(
+            &mut buffer,
+            &Err(QuoteGenerationError::BadProvider("sev_guest".to_string())),
+        )
+        .unwrap();
+
+        match read_response(&mut Cursor::new(buffer)) {
+            Err(QuoteServiceError::Remote(kind, message)) => {
+                assert_eq!(kind, RemoteErrorKind::BadProvider);
+                assert!(message.contains("sev_guest"));
+            }
+            other => panic!("expected a Remote error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn response_rejects_oversized_quote_length_before_allocating() {
+        let mut buffer = Vec::new();
+        buffer.push(0u8); // Ok status
+        buffer.extend_from_slice(&(MAX_RESPONSE_LEN + 1).to_le_bytes());
+        // No body: a well-behaved read must reject based on the length prefix alone.
+
+        match read_response(&mut Cursor::new(buffer)) {
+            Err(QuoteServiceError::ResponseTooLarge(len)) => {
+                assert_eq!(len, MAX_RESPONSE_LEN + 1);
+            }
+            other => panic!("expected ResponseTooLarge, got {:?}", other),
+        }
+    }
+}